@@ -1,13 +1,27 @@
 use std::borrow::Cow;
+use std::cmp;
+use std::collections::HashMap;
 use std::path::{Path, PathBuf};
+use std::io::{Read, Seek, SeekFrom};
+use std::io;
 use std::io::ErrorKind::NotFound;
 use std::fs;
 use std::str::Utf8Error;
+use std::sync::Arc;
+use std::time::UNIX_EPOCH;
 
 use hyper::method::Method::{Get, Head};
+use hyper::header::{AcceptRanges, ByteRangeSpec, ContentLength, ContentRange, ContentRangeSpec,
+                     ContentType, ETag, EntityTag, HttpDate, IfModifiedSince, IfNoneMatch,
+                     LastModified, Range, RangeUnit};
+use hyper::mime::Mime;
+use mime_guess;
 use percent_encoding;
+use pulldown_cmark::{self, Parser};
+use time::{self, Timespec};
 
 use NickelError;
+use mimes::MediaType;
 use status::StatusCode;
 use request::Request;
 use response::Response;
@@ -17,7 +31,17 @@ use middleware::{Middleware, MiddlewareResult};
 
 #[derive(Clone)]
 pub struct StaticFilesHandler {
-    root_path: PathBuf
+    root_path: PathBuf,
+    show_index: bool,
+    index_files: Vec<String>,
+    fallback_file: Option<String>,
+    mime_overrides: HashMap<String, Mime>,
+    mime_callback: Option<Arc<Fn(&Path, Mime) -> Mime + Send + Sync>>,
+    hide_dotfiles: bool,
+    denylist: Vec<PathBuf>,
+    path_filter: Option<Arc<Fn(&Path) -> bool + Send + Sync>>,
+    markdown: bool,
+    markdown_template: Option<String>,
 }
 
 impl<D> Middleware<D> for StaticFilesHandler {
@@ -29,7 +53,9 @@ impl<D> Middleware<D> for StaticFilesHandler {
                     Some(path) => {
                         let path = Self::percent_decode(path);
                         match path {
-                            Ok(path) => self.with_file(Path::new(path.as_ref()), res),
+                            // own the decoded path so we can borrow `req` again below
+                            // for the conditional/range headers.
+                            Ok(path) => self.with_file(Path::new(&*path).to_path_buf(), req, res),
                             Err(e) => Err(NickelError::new(res, e.to_string(), StatusCode::BadRequest))
                         }
                     }
@@ -56,16 +82,229 @@ impl StaticFilesHandler {
     /// ```
     pub fn new<P: AsRef<Path>>(root_path: P) -> StaticFilesHandler {
         StaticFilesHandler {
-            root_path: root_path.as_ref().to_path_buf()
+            root_path: root_path.as_ref().to_path_buf(),
+            show_index: false,
+            index_files: vec!["index.html".to_string()],
+            fallback_file: None,
+            mime_overrides: HashMap::new(),
+            mime_callback: None,
+            hide_dotfiles: false,
+            denylist: Vec::new(),
+            path_filter: None,
+            markdown: false,
+            markdown_template: None,
         }
     }
 
+    /// Enable or disable directory index listings. Disabled by default so
+    /// existing applications are unaffected by this feature. When enabled, a
+    /// request that resolves to a directory with none of the configured index
+    /// files (see `index_files`, `index.html` by default) renders a
+    /// listing of that directory's contents instead of falling through to
+    /// the next middleware.
+    ///
+    /// # Examples
+    /// ```{rust}
+    /// use nickel::{Nickel, StaticFilesHandler};
+    /// let mut server = Nickel::new();
+    ///
+    /// server.utilize(StaticFilesHandler::new("/path/to/serve/").show_index(true));
+    /// ```
+    pub fn show_index(mut self, show_index: bool) -> StaticFilesHandler {
+        self.show_index = show_index;
+        self
+    }
+
+    /// Set the ordered list of filenames tried, in turn, when a request
+    /// resolves to a directory. Defaults to `["index.html"]`.
+    ///
+    /// # Examples
+    /// ```{rust}
+    /// use nickel::{Nickel, StaticFilesHandler};
+    /// let mut server = Nickel::new();
+    ///
+    /// server.utilize(StaticFilesHandler::new("/path/to/serve/")
+    ///                     .index_files(vec!["index.html", "index.htm"]));
+    /// ```
+    pub fn index_files<I, S>(mut self, index_files: I) -> StaticFilesHandler
+            where I: IntoIterator<Item = S>, S: Into<String> {
+        self.index_files = index_files.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Set a fallback file, relative to the root path, served with `200` for
+    /// any otherwise-`404` `Get`/`Head` request whose `Accept` header
+    /// indicates HTML. This enables single-page-app routing, where
+    /// client-side routes such as `/users/42` must return the app shell.
+    ///
+    /// # Examples
+    /// ```{rust}
+    /// use nickel::{Nickel, StaticFilesHandler};
+    /// let mut server = Nickel::new();
+    ///
+    /// server.utilize(StaticFilesHandler::new("/path/to/serve/")
+    ///                     .fallback_file("index.html"));
+    /// ```
+    pub fn fallback_file<S: Into<String>>(mut self, fallback_file: S) -> StaticFilesHandler {
+        self.fallback_file = Some(fallback_file.into());
+        self
+    }
+
+    /// Force the `Content-Type` used for files with the given extension
+    /// (without the leading dot), overriding the built-in guess.
+    ///
+    /// # Examples
+    /// ```{rust}
+    /// use nickel::{Nickel, StaticFilesHandler};
+    /// use nickel::hyper::mime::{Mime, TopLevel, SubLevel};
+    /// let mut server = Nickel::new();
+    ///
+    /// server.utilize(StaticFilesHandler::new("/path/to/serve/")
+    ///                     .add_mime("wasm", Mime(TopLevel::Application, SubLevel::Ext("wasm".into()), vec![])));
+    /// ```
+    pub fn add_mime<S: Into<String>>(mut self, ext: S, mime: Mime) -> StaticFilesHandler {
+        self.mime_overrides.insert(ext.into(), mime);
+        self
+    }
+
+    /// Register a callback invoked with the resolved path and guessed/overridden
+    /// `Mime` just before a file is sent, letting operators adjust the final
+    /// `Content-Type`, e.g. to set a charset.
+    pub fn mime_callback<F>(mut self, callback: F) -> StaticFilesHandler
+            where F: Fn(&Path, Mime) -> Mime + Send + Sync + 'static {
+        self.mime_callback = Some(Arc::new(callback));
+        self
+    }
+
+    /// Resolve the `Content-Type` for `path`: an extension override takes
+    /// precedence over the built-in guess, and the user callback, if any,
+    /// gets the final say.
+    fn resolve_mime(&self, path: &Path) -> Mime {
+        let guessed = mime_guess::guess_mime_type(path);
+
+        let mime = path.extension()
+            .and_then(|ext| ext.to_str())
+            .and_then(|ext| self.mime_overrides.get(ext))
+            .cloned()
+            .unwrap_or(guessed);
+
+        match self.mime_callback {
+            Some(ref callback) => callback(path, mime),
+            None => mime
+        }
+    }
+
+    /// Reject requests for dotfiles (any path component beginning with `.`)
+    /// as if they didn't exist, rather than leaking their presence via a
+    /// `403`. Disabled by default.
+    pub fn hide_dotfiles(mut self, hide_dotfiles: bool) -> StaticFilesHandler {
+        self.hide_dotfiles = hide_dotfiles;
+        self
+    }
+
+    /// Set an explicit denylist of paths, relative to the root path, that
+    /// should never be served (e.g. `.git`, `secrets.toml`). A denylisted
+    /// directory also hides everything beneath it.
+    ///
+    /// # Examples
+    /// ```{rust}
+    /// use nickel::{Nickel, StaticFilesHandler};
+    /// let mut server = Nickel::new();
+    ///
+    /// server.utilize(StaticFilesHandler::new("/path/to/serve/")
+    ///                     .denylist(vec![".git", "secrets.toml"]));
+    /// ```
+    pub fn denylist<I, P>(mut self, denylist: I) -> StaticFilesHandler
+            where I: IntoIterator<Item = P>, P: Into<PathBuf> {
+        self.denylist = denylist.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Register a predicate that must return `true` for a (root-relative)
+    /// path to be served. Lets operators implement custom access rules
+    /// beyond the dotfile check and denylist.
+    pub fn path_filter<F>(mut self, filter: F) -> StaticFilesHandler
+            where F: Fn(&Path) -> bool + Send + Sync + 'static {
+        self.path_filter = Some(Arc::new(filter));
+        self
+    }
+
+    /// Whether `path` (relative to the root path) is blocked by the dotfile
+    /// rule, the denylist, or the user's path filter.
+    fn is_denied(&self, path: &Path) -> bool {
+        if self.hide_dotfiles && has_dotfile_component(path) {
+            return true;
+        }
+
+        if self.denylist.iter().any(|denied| path == denied || path.starts_with(denied)) {
+            return true;
+        }
+
+        match self.path_filter {
+            Some(ref filter) => !filter(path),
+            None => false
+        }
+    }
+
+    /// Render `.md`/`.markdown` files to HTML on the fly instead of serving
+    /// their raw source. Disabled by default, so the default behavior stays
+    /// byte-for-byte file serving. A request with a `?raw=1` query flag
+    /// always bypasses rendering.
+    ///
+    /// # Examples
+    /// ```{rust}
+    /// use nickel::{Nickel, StaticFilesHandler};
+    /// let mut server = Nickel::new();
+    ///
+    /// server.utilize(StaticFilesHandler::new("/path/to/serve/").markdown(true));
+    /// ```
+    pub fn markdown(mut self, markdown: bool) -> StaticFilesHandler {
+        self.markdown = markdown;
+        self
+    }
+
+    /// Set the HTML template the rendered Markdown body is wrapped in. The
+    /// template must contain a `{{content}}` placeholder for the rendered
+    /// body. Defaults to a minimal bare-bones document.
+    pub fn markdown_template<S: Into<String>>(mut self, template: S) -> StaticFilesHandler {
+        self.markdown_template = Some(template.into());
+        self
+    }
+
+    /// Convert Markdown `source` to a full HTML document, using the
+    /// configured template if one was set.
+    fn render_markdown(&self, source: &str) -> String {
+        let mut body = String::new();
+        pulldown_cmark::html::push_html(&mut body, Parser::new(source));
+
+        match self.markdown_template {
+            Some(ref template) => template.replace("{{content}}", &body),
+            None => format!(
+                "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\"></head><body>\n{}\n</body></html>\n",
+                body)
+        }
+    }
+
+    /// Read `path` as Markdown source and respond with its rendered HTML.
+    fn serve_markdown<'a, D>(&self, path: &Path, mut res: Response<'a, D>)
+            -> MiddlewareResult<'a, D> {
+        let mut source = String::new();
+        let read = fs::File::open(path).and_then(|mut file| file.read_to_string(&mut source));
+
+        if let Err(e) = read {
+            return res.error(StatusCode::InternalServerError, e.to_string());
+        }
+
+        res.set(MediaType::Html);
+        res.send(self.render_markdown(&source))
+    }
+
     fn extract_path<'a, D>(&self, req: &'a mut Request<D>) -> Option<&'a str> {
         req.path_without_query().map(|path| {
             debug!("{:?} {:?}{:?}", req.origin.method, self.root_path.display(), path);
 
             match path {
-                "/" => "index.html",
+                "/" => "",
                 path => &path[1..],
             }
         })
@@ -75,8 +314,9 @@ impl StaticFilesHandler {
         percent_encoding::percent_decode(path.as_bytes()).decode_utf8()
     }
 
-    fn with_file<'a, 'b, D, P>(&self,
+    fn with_file<'a, D, P>(&self,
                             path: P,
+                            req: &Request<D>,
                             res: Response<'a, D>)
             -> MiddlewareResult<'a, D> where P: AsRef<Path> {
         let path = path.as_ref();
@@ -85,18 +325,307 @@ impl StaticFilesHandler {
             return res.error(StatusCode::BadRequest, log_msg);
         }
 
+        if self.is_denied(path) {
+            return self.serve_fallback(req, res);
+        }
+
         let path = self.root_path.join(path);
         match fs::metadata(&path) {
-            Ok(ref attr) if attr.is_file() => res.send_file(&path),
+            Ok(ref attr) if attr.is_file() => self.serve_file(&path, attr, req, res),
+            Ok(ref attr) if attr.is_dir() => {
+                match self.find_index_file(&path) {
+                    Some((index_path, index_attr)) =>
+                        self.serve_file(&index_path, &index_attr, req, res),
+                    None if self.show_index => self.serve_index(&path, req, res),
+                    None => res.next_middleware()
+                }
+            }
             Err(ref e) if e.kind() != NotFound => {
                 debug!("Error getting metadata for file '{:?}': {:?}", path, e);
                 res.next_middleware()
             }
-            _ => res.next_middleware()
+            _ => self.serve_fallback(req, res)
+        }
+    }
+
+    /// Try each configured index filename in `dir`, in order, returning the
+    /// first that exists, is a regular file and is not blocked by `is_denied`.
+    fn find_index_file(&self, dir: &Path) -> Option<(PathBuf, fs::Metadata)> {
+        self.index_files.iter()
+            .map(|name| dir.join(name))
+            .filter(|candidate| {
+                let rel = candidate.strip_prefix(&self.root_path).unwrap_or(candidate.as_path());
+                !self.is_denied(rel)
+            })
+            .filter_map(|candidate| {
+                fs::metadata(&candidate).ok()
+                    .into_iter()
+                    .find(|attr| attr.is_file())
+                    .map(|attr| (candidate, attr))
+            })
+            .next()
+    }
+
+    /// Serve the configured SPA fallback file for a `404` request that
+    /// prefers HTML, otherwise defer to the next middleware. Only ever
+    /// reached via `with_file`, which `invoke` restricts to `Get`/`Head`.
+    fn serve_fallback<'a, D>(&self, req: &Request<D>, res: Response<'a, D>)
+            -> MiddlewareResult<'a, D> {
+        if wants_html(req) {
+            if let Some(ref fallback_file) = self.fallback_file {
+                let fallback_path = self.root_path.join(fallback_file);
+                if let Ok(attr) = fs::metadata(&fallback_path) {
+                    if attr.is_file() {
+                        return self.serve_file(&fallback_path, &attr, req, res);
+                    }
+                }
+            }
+        }
+
+        res.next_middleware()
+    }
+
+    /// Render a directory listing as HTML or JSON, chosen by the request's
+    /// `Accept` header.
+    fn serve_index<'a, D>(&self, path: &Path, req: &Request<D>, res: Response<'a, D>)
+            -> MiddlewareResult<'a, D> {
+        let rel_dir = path.strip_prefix(&self.root_path).unwrap_or(Path::new(""));
+        let entries = match self.list_dir(path, rel_dir) {
+            Ok(entries) => entries,
+            Err(e) => return res.error(StatusCode::InternalServerError, e.to_string())
+        };
+
+        if wants_json(req) {
+            render_index_json(entries, res)
+        } else {
+            render_index_html(&entries, res)
+        }
+    }
+
+    /// Enumerate a directory's entries, sorted by name, dropping anything
+    /// `is_denied` would block so a listing can't leak the existence of
+    /// dotfiles or denylisted paths it wouldn't serve directly.
+    fn list_dir(&self, path: &Path, rel_dir: &Path) -> io::Result<Vec<DirEntryInfo>> {
+        let mut entries = Vec::new();
+
+        for entry in try!(fs::read_dir(path)) {
+            let entry = try!(entry);
+            let metadata = try!(entry.metadata());
+            let name = entry.file_name();
+
+            if self.is_denied(&rel_dir.join(&name)) {
+                continue;
+            }
+
+            entries.push(DirEntryInfo {
+                name: name.to_string_lossy().into_owned(),
+                is_dir: metadata.is_dir(),
+                size: metadata.len(),
+            });
+        }
+
+        entries.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(entries)
+    }
+
+    fn serve_file<'a, D>(&self,
+                         path: &Path,
+                         attr: &fs::Metadata,
+                         req: &Request<D>,
+                         mut res: Response<'a, D>)
+            -> MiddlewareResult<'a, D> {
+        let total = attr.len();
+
+        // Validators are computed from the source file's metadata, so a 304
+        // can short-circuit before markdown is re-read and re-rendered.
+        let last_modified = last_modified_of(attr);
+        let etag = EntityTag::weak(format!("{}-{}", total, last_modified.0.to_timespec().sec));
+        res.set(LastModified(last_modified.clone()));
+        res.set(ETag(etag.clone()));
+
+        if is_not_modified(req, &last_modified, &etag) {
+            // A 304 carries only validators (RFC 7232 SS4.1), not
+            // representation headers, so Accept-Ranges/Content-Type are set
+            // below this check rather than above it.
+            res.set(StatusCode::NotModified);
+            return res.send("");
+        }
+
+        res.set(AcceptRanges(vec![RangeUnit::Bytes]));
+        res.set(ContentType(self.resolve_mime(path)));
+
+        if self.markdown && is_markdown_file(path) && !wants_raw(req) {
+            return self.serve_markdown(path, res);
+        }
+
+        // Only a single byte-range is honoured. Multiple ranges, unparsable
+        // headers, or the header being absent all fall back to a full 200.
+        match req.origin.headers.get::<Range>() {
+            Some(&Range::Bytes(ref specs)) if specs.len() == 1 => {
+                match resolve_range(&specs[0], total) {
+                    Some((start, end)) => send_range(res, path, start, end, total),
+                    None => {
+                        res.set(ContentRange(ContentRangeSpec::Bytes {
+                            range: None,
+                            instance_length: Some(total),
+                        }));
+                        let log_msg = format!("Range not satisfiable for '{:?}'", path);
+                        res.error(StatusCode::RangeNotSatisfiable, log_msg)
+                    }
+                }
+            }
+            _ => res.send_file(path)
         }
     }
 }
 
+/// Resolve a single `Range` header byte-range-spec against the total length
+/// of the resource, returning an inclusive `(start, end)` pair or `None` if
+/// the range cannot be satisfied.
+fn resolve_range(spec: &ByteRangeSpec, total: u64) -> Option<(u64, u64)> {
+    if total == 0 {
+        return None;
+    }
+
+    let last = total - 1;
+    match *spec {
+        ByteRangeSpec::FromTo(start, end) if start <= last => Some((start, cmp::min(end, last))),
+        ByteRangeSpec::AllFrom(start) if start <= last => Some((start, last)),
+        ByteRangeSpec::Last(0) => None,
+        ByteRangeSpec::Last(n) => Some((total - cmp::min(n, total), last)),
+        _ => None
+    }
+}
+
+/// A single entry in a rendered directory listing.
+#[derive(RustcEncodable)]
+struct DirEntryInfo {
+    name: String,
+    is_dir: bool,
+    size: u64,
+}
+
+/// Whether the request prefers a JSON directory listing over HTML.
+fn wants_json<D>(req: &Request<D>) -> bool {
+    req.origin.headers.get_raw("Accept")
+        .and_then(|values| values.get(0))
+        .map_or(false, |value| {
+            String::from_utf8_lossy(value).contains("application/json")
+        })
+}
+
+/// Whether the request's `Accept` header indicates a preference for HTML.
+/// Absent an `Accept` header, HTML is assumed so plain navigations still
+/// trigger the SPA fallback.
+fn wants_html<D>(req: &Request<D>) -> bool {
+    req.origin.headers.get_raw("Accept")
+        .and_then(|values| values.get(0))
+        .map_or(true, |value| {
+            let accept = String::from_utf8_lossy(value);
+            accept.contains("text/html") || accept.contains("*/*")
+        })
+}
+
+fn render_index_json<'a, D>(entries: Vec<DirEntryInfo>, mut res: Response<'a, D>)
+        -> MiddlewareResult<'a, D> {
+    res.set(MediaType::Json);
+    res.send(entries)
+}
+
+fn render_index_html<'a, D>(entries: &[DirEntryInfo], mut res: Response<'a, D>)
+        -> MiddlewareResult<'a, D> {
+    res.set(MediaType::Html);
+
+    let mut body = String::from(
+        "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\"></head><body>\n\
+         <table>\n<tr><th>Name</th><th>Type</th><th>Size</th></tr>\n");
+
+    for entry in entries {
+        // `entry.name` comes straight from `fs::read_dir`, so it can't
+        // contain `..` or path separators to escape the listed directory.
+        let href = percent_encoding::percent_encode(entry.name.as_bytes(),
+                                                     percent_encoding::DEFAULT_ENCODE_SET);
+        let suffix = if entry.is_dir { "/" } else { "" };
+
+        body.push_str(&format!(
+            "<tr><td><a href=\"{href}{suffix}\">{name}{suffix}</a></td>\
+             <td>{kind}</td><td>{size}</td></tr>\n",
+            href = href,
+            suffix = suffix,
+            name = escape_html(&entry.name),
+            kind = if entry.is_dir { "directory" } else { "file" },
+            size = entry.size));
+    }
+
+    body.push_str("</table>\n</body></html>\n");
+    res.send(body)
+}
+
+fn escape_html(input: &str) -> String {
+    input.replace('&', "&amp;")
+         .replace('<', "&lt;")
+         .replace('>', "&gt;")
+         .replace('"', "&quot;")
+}
+
+/// Build the `Last-Modified` header value from a file's metadata, falling
+/// back to the Unix epoch if the platform can't report a modification time.
+fn last_modified_of(attr: &fs::Metadata) -> HttpDate {
+    let secs = attr.modified()
+        .ok()
+        .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+        .map_or(0, |duration| duration.as_secs());
+
+    HttpDate(time::at_utc(Timespec::new(secs as i64, 0)))
+}
+
+/// Determine whether the client's cached copy, as described by
+/// `If-None-Match`/`If-Modified-Since`, is still current. `If-None-Match`
+/// takes precedence over `If-Modified-Since` when both are present.
+fn is_not_modified<D>(req: &Request<D>, last_modified: &HttpDate, etag: &EntityTag) -> bool {
+    if let Some(if_none_match) = req.origin.headers.get::<IfNoneMatch>() {
+        return match *if_none_match {
+            IfNoneMatch::Any => true,
+            IfNoneMatch::Items(ref tags) => tags.iter().any(|tag| tag.weak_eq(etag))
+        };
+    }
+
+    req.origin.headers.get::<IfModifiedSince>()
+        .map_or(false, |since| since.0.to_timespec() >= last_modified.0.to_timespec())
+}
+
+/// Stream the `[start, end]` (inclusive) byte window of the file at `path`
+/// as a `206 Partial Content` response.
+fn send_range<'a, D>(mut res: Response<'a, D>,
+                      path: &Path,
+                      start: u64,
+                      end: u64,
+                      total: u64)
+        -> MiddlewareResult<'a, D> {
+    let mut file = match fs::File::open(path) {
+        Ok(file) => file,
+        Err(e) => return res.error(StatusCode::InternalServerError, e.to_string())
+    };
+
+    if let Err(e) = file.seek(SeekFrom::Start(start)) {
+        return res.error(StatusCode::InternalServerError, e.to_string());
+    }
+
+    let len = end - start + 1;
+
+    res.set(StatusCode::PartialContent);
+    res.set(ContentRange(ContentRangeSpec::Bytes {
+        range: Some((start, end)),
+        instance_length: Some(total),
+    }));
+    res.set(ContentLength(len));
+
+    // Stream the window straight from the open file rather than buffering
+    // it, so a range over most/all of a large file doesn't get materialized
+    // into memory first.
+    res.send(file.take(len))
+}
+
 /// Block paths from accessing the parent directory
 fn safe_path<P: AsRef<Path>>(path: P) -> bool {
     use std::path::Component;
@@ -108,6 +637,37 @@ fn safe_path<P: AsRef<Path>>(path: P) -> bool {
     })
 }
 
+/// Whether `path` has a `.md`/`.markdown` extension.
+fn is_markdown_file(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .map_or(false, |ext| ext.eq_ignore_ascii_case("md") || ext.eq_ignore_ascii_case("markdown"))
+}
+
+/// Whether the request asked to bypass Markdown rendering via `?raw=1`.
+fn wants_raw<D>(req: &Request<D>) -> bool {
+    use hyper::uri::RequestUri;
+
+    match req.origin.uri {
+        RequestUri::AbsolutePath(ref uri) => {
+            uri.splitn(2, '?')
+                .nth(1)
+                .map_or(false, |query| query.split('&').any(|pair| pair == "raw=1"))
+        }
+        _ => false
+    }
+}
+
+/// Whether any component of `path` is a dotfile/dotdir name.
+fn has_dotfile_component(path: &Path) -> bool {
+    use std::path::Component;
+
+    path.components().any(|c| match c {
+        Component::Normal(name) => name.to_str().map_or(false, |name| name.starts_with('.')),
+        _ => false
+    })
+}
+
 #[test]
 fn bad_paths() {
     let bad_paths = &[
@@ -137,3 +697,43 @@ fn valid_paths() {
         assert!(safe_path(path), "expected {:?} to not be suspicious", path);
     }
 }
+
+#[test]
+fn resolves_simple_range() {
+    assert_eq!(resolve_range(&ByteRangeSpec::FromTo(0, 9), 100), Some((0, 9)));
+}
+
+#[test]
+fn resolves_open_ended_range() {
+    assert_eq!(resolve_range(&ByteRangeSpec::AllFrom(90), 100), Some((90, 99)));
+}
+
+#[test]
+fn resolves_suffix_range() {
+    assert_eq!(resolve_range(&ByteRangeSpec::Last(500), 100), Some((0, 99)));
+}
+
+#[test]
+fn rejects_unsatisfiable_range() {
+    assert_eq!(resolve_range(&ByteRangeSpec::FromTo(200, 300), 100), None);
+}
+
+#[test]
+fn detects_markdown_files() {
+    assert!(is_markdown_file(Path::new("README.md")));
+    assert!(is_markdown_file(Path::new("docs/guide.MARKDOWN")));
+    assert!(!is_markdown_file(Path::new("index.html")));
+}
+
+#[test]
+fn detects_dotfile_components() {
+    assert!(has_dotfile_component(Path::new(".git/config")));
+    assert!(has_dotfile_component(Path::new("foo/.env")));
+    assert!(!has_dotfile_component(Path::new("foo/bar.txt")));
+}
+
+#[test]
+fn escapes_html_entities_in_names() {
+    assert_eq!(escape_html("<script>&\"</script>"),
+               "&lt;script&gt;&amp;&quot;&lt;/script&gt;");
+}